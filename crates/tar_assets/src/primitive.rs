@@ -1,7 +1,7 @@
 use std::{sync::Arc, path::Path};
 
 use bytemuck::{Pod, Zeroable};
-use cgmath::{Vector4, Vector3, Vector2, Zero, Matrix4};
+use cgmath::{Vector4, Vector3, Vector2, Zero, Matrix4, InnerSpace};
 
 use crate::{material::Material, shader::{PbrShader, ShaderFlags, MaterialInput}, scene::ImportData, Error, Result, root::Root, WgpuInfo, Vec4Slice, Vec3Slice};
 
@@ -103,6 +103,27 @@ pub struct Instance {
     normal: [[f32; 3]; 3],
 }
 impl Instance {
+    /// Builds a per-instance row from a model matrix, deriving the normal matrix
+    /// as the inverse-transpose of the upper-left 3x3 so non-uniform scales keep
+    /// normals orthogonal to the surface.
+    pub fn new(model_matrix: &Matrix4<f32>) -> Self {
+        use cgmath::{Matrix, Matrix3, SquareMatrix};
+        let model = *model_matrix;
+        let normal = Matrix3::from_cols(
+            model.x.truncate(),
+            model.y.truncate(),
+            model.z.truncate(),
+        )
+        .invert()
+        .map(|m| m.transpose())
+        .unwrap_or_else(Matrix3::identity);
+
+        Self {
+            model: model.into(),
+            normal: normal.into(),
+        }
+    }
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem;
         wgpu::VertexBufferLayout {
@@ -164,6 +185,13 @@ pub struct Primitive {
 
     pub material: Arc<Material>,
     pub pbr_shader: Arc<PbrShader>,
+
+    /// GPU skinning resources, present only for primitives carrying joints and
+    /// weights. When set, the render pass draws from [`Skin::output`] instead of
+    /// the static vertex buffer.
+    ///
+    /// [`Skin::output`]: crate::skin::Skin::output
+    pub skin: Option<crate::skin::Skin>,
 }
 
 impl Primitive {
@@ -182,6 +210,7 @@ impl Primitive {
             indices: None,
             material,
             pbr_shader: shader,
+            skin: None,
         };
 
         prim.setup_primitive(vertices, indices, w_info);
@@ -215,20 +244,27 @@ impl Primitive {
 
         let mut shader_flags = ShaderFlags::empty();
 
-        if let Some(normals) = reader.read_normals() {
+        let has_normals = if let Some(normals) = reader.read_normals() {
             for (i, normal) in normals.enumerate() {
                 vertices[i].normal = normal;
             }
+            true
+        } else {
+            false
+        };
+        if has_normals {
             shader_flags |= ShaderFlags::HAS_NORMALS;
         }
-        else {
-            return Err(Error::NotSupported("normal calculation".to_owned()))
-        }
 
-        if let Some(tangents) = reader.read_tangents() {
+        let has_tangents = if let Some(tangents) = reader.read_tangents() {
             for (i, tangent) in tangents.enumerate() {
                 vertices[i].tangent = tangent;
             }
+            true
+        } else {
+            false
+        };
+        if has_tangents {
             shader_flags |= ShaderFlags::HAS_TANGENTS;
         }
 
@@ -291,6 +327,90 @@ impl Primitive {
                 read_indices.into_u32().collect::<Vec<_>>()
             });
 
+        // The triangle list the mesh is built from, used to accumulate per-vertex
+        // normals/tangents. Falls back to sequential triples when non-indexed.
+        let triangles: Vec<[usize; 3]> = match &indices {
+            Some(indices) => indices
+                .chunks_exact(3)
+                .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+                .collect(),
+            None => (0..vertices.len())
+                .step_by(3)
+                .filter(|&i| i + 2 < vertices.len())
+                .map(|i| [i, i + 1, i + 2])
+                .collect(),
+        };
+
+        // glTF omits normals often enough that we generate them ourselves: the
+        // area-weighted average of adjacent face normals, normalised per vertex.
+        if !has_normals {
+            for vertex in vertices.iter_mut() {
+                vertex.normal = [0.0; 3];
+            }
+            for &[i0, i1, i2] in &triangles {
+                let p0 = Vector3::from(vertices[i0].position);
+                let p1 = Vector3::from(vertices[i1].position);
+                let p2 = Vector3::from(vertices[i2].position);
+                // Magnitude encodes twice the triangle area, weighting the average.
+                let face = (p1 - p0).cross(p2 - p0);
+                for &i in &[i0, i1, i2] {
+                    let n = Vector3::from(vertices[i].normal) + face;
+                    vertices[i].normal = n.into();
+                }
+            }
+            for vertex in vertices.iter_mut() {
+                let n = Vector3::from(vertex.normal);
+                if n.magnitude2() > 0.0 {
+                    vertex.normal = n.normalize().into();
+                }
+            }
+            shader_flags |= ShaderFlags::HAS_NORMALS;
+        }
+
+        // Tangents (for normal mapping) via Lengyel's method when UVs exist but
+        // no TANGENT attribute was supplied.
+        if !has_tangents && shader_flags.contains(ShaderFlags::HAS_UV) {
+            let mut tangents = vec![Vector3::<f32>::zero(); vertices.len()];
+            let mut bitangents = vec![Vector3::<f32>::zero(); vertices.len()];
+            for &[i0, i1, i2] in &triangles {
+                let p0 = Vector3::from(vertices[i0].position);
+                let p1 = Vector3::from(vertices[i1].position);
+                let p2 = Vector3::from(vertices[i2].position);
+                let e1 = p1 - p0;
+                let e2 = p2 - p0;
+
+                let uv0 = Vector2::from(vertices[i0].tex_coord_0);
+                let uv1 = Vector2::from(vertices[i1].tex_coord_0);
+                let uv2 = Vector2::from(vertices[i2].tex_coord_0);
+                let (du1, dv1) = (uv1.x - uv0.x, uv1.y - uv0.y);
+                let (du2, dv2) = (uv2.x - uv0.x, uv2.y - uv0.y);
+
+                let r = 1.0 / (du1 * dv2 - du2 * dv1);
+                if !r.is_finite() {
+                    continue; // degenerate UVs: leave the default tangent
+                }
+                let tangent = (e1 * dv2 - e2 * dv1) * r;
+                let bitangent = (e2 * du1 - e1 * du2) * r;
+                for &i in &[i0, i1, i2] {
+                    tangents[i] += tangent;
+                    bitangents[i] += bitangent;
+                }
+            }
+            for (i, vertex) in vertices.iter_mut().enumerate() {
+                let n = Vector3::from(vertex.normal);
+                let t = tangents[i];
+                if t.magnitude2() <= 0.0 {
+                    continue;
+                }
+                // Gram-Schmidt orthogonalise against the normal.
+                let t = (t - n * n.dot(t)).normalize();
+                // Store handedness in w so the shader can reconstruct the bitangent.
+                let w = if n.cross(t).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+                vertex.tangent = [t.x, t.y, t.z, w];
+            }
+            shader_flags |= ShaderFlags::HAS_TANGENTS;
+        }
+
         let g_material = g_primitive.material();
 
         let mut material = None;
@@ -352,16 +472,109 @@ impl Primitive {
         }
     }
 
-    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, model_matrix: &Matrix4<f32>, mvp_matrix: &Matrix4<f32>, camera_position: &Vector3<f32>) {
-        // TODO!: determine if shader+material already active to reduce work...
+    /// The [`Phase`] this primitive is sorted into by the [`RenderGraph`], derived
+    /// from its material's alpha mode.
+    ///
+    /// [`Phase`]: crate::render_graph::Phase
+    /// [`RenderGraph`]: crate::render_graph::RenderGraph
+    pub fn phase(&self) -> crate::render_graph::Phase {
+        if self.material.is_transparent() {
+            crate::render_graph::Phase::Transparent
+        } else {
+            crate::render_graph::Phase::Opaque
+        }
+    }
 
-        // render_pass.set_pipeline(&self.pbr_shader.pipeline);
-        // render_pass.set_vertex_buffer(0, self.vertices.unwrap().slice(..));
-        // render_pass.set_index_buffer(self.indices.unwrap().slice(..), wgpu::IndexFormat::Uint32);
-        // render_pass.set_bind_group(0, &self.pbr_shader.uniforms.bind_group.unwrap(), &[]);
-        // //TODO!: texture and camera bind_groups
-        // //TODO!: correct instances
-        // render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    /// Records this primitive into `render_pass` as a single instance drawn from
+    /// row `instance` of `instance_buffer`. The caller uploads the per-draw
+    /// [`Instance`] transform into that slot beforehand (see
+    /// [`RenderGraph::flush`](crate::render_graph::RenderGraph::flush)); binding
+    /// the transform per-instance rather than rewriting a shared uniform keeps
+    /// every draw in the pass observing its own model matrix.
+    ///
+    /// `state` tracks the pipeline and material bind group currently bound on the
+    /// pass so that back-to-back draws sharing a shader+material skip the
+    /// redundant `set_pipeline`/`set_bind_group` calls.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        instance_buffer: &'a wgpu::Buffer,
+        instance: u32,
+        state: &mut crate::render_graph::BoundState,
+    ) {
+        if state.bind_shader(&self.pbr_shader) {
+            render_pass.set_pipeline(&self.pbr_shader.pipeline);
+            render_pass.set_bind_group(0, &self.pbr_shader.uniforms.bind_group, &[]);
+        }
+        if state.bind_material(&self.material) {
+            render_pass.set_bind_group(1, &self.material.bind_group, &[]);
+        }
+
+        let vertices = match self.skin.as_ref() {
+            Some(skin) => &skin.output,
+            None => self.vertices.as_ref().expect("primitive has no vertex buffer"),
+        };
+        render_pass.set_vertex_buffer(0, vertices.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+        if let Some(indices) = self.indices.as_ref() {
+            render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..self.num_indices, 0, instance..instance + 1);
+        } else {
+            render_pass.draw(0..self.num_vertices, instance..instance + 1);
+        }
+    }
 
+    /// Records this primitive's skinning dispatch into `compute_pass`, uploading
+    /// `joint_matrices` for this frame. The caller must have bound the
+    /// [`SkinPipeline`](crate::skin::SkinPipeline) on the pass beforehand. A no-op
+    /// for primitives without joints/weights.
+    pub fn skin<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>, joint_matrices: &[Matrix4<f32>]) {
+        if let Some(skin) = self.skin.as_ref() {
+            skin.dispatch(compute_pass, joint_matrices);
+        }
+    }
+
+    /// Records this primitive once for every row in `instances`, binding the
+    /// batched instance buffer at vertex slot 1 and issuing a single
+    /// `draw_indexed`/`draw` covering `0..instances.len()`.
+    ///
+    /// The caller is responsible for having uploaded `instances` into
+    /// `instance_buffer` (see [`InstanceBuffer`](crate::render_graph::InstanceBuffer));
+    /// the pipeline/material bind groups are bound through `state` exactly as in
+    /// [`draw`](Self::draw) so shared batches skip redundant state changes.
+    pub fn draw_instanced<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        instance_buffer: &'a wgpu::Buffer,
+        instances: &[Instance],
+        state: &mut crate::render_graph::BoundState,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+
+        if state.bind_shader(&self.pbr_shader) {
+            render_pass.set_pipeline(&self.pbr_shader.pipeline);
+            render_pass.set_bind_group(0, &self.pbr_shader.uniforms.bind_group, &[]);
+        }
+        if state.bind_material(&self.material) {
+            render_pass.set_bind_group(1, &self.material.bind_group, &[]);
+        }
+
+        let vertices = match self.skin.as_ref() {
+            Some(skin) => &skin.output,
+            None => self.vertices.as_ref().expect("primitive has no vertex buffer"),
+        };
+        render_pass.set_vertex_buffer(0, vertices.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+        let count = instances.len() as u32;
+        if let Some(indices) = self.indices.as_ref() {
+            render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..self.num_indices, 0, 0..count);
+        } else {
+            render_pass.draw(0..self.num_vertices, 0..count);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cgmath::{Matrix4, Vector3, InnerSpace, Transform};
+use rayon::prelude::*;
+
+use crate::{material::Material, primitive::{Instance, Primitive}, shader::PbrShader, WgpuInfo};
+
+/// Number of primitives recorded per [`wgpu::RenderBundle`] when recording a
+/// phase in parallel. Larger chunks mean fewer bundles but coarser load balance.
+const BUNDLE_CHUNK: usize = 64;
+
+/// The phase a [`Primitive`] is sorted into. Opaque geometry is drawn first
+/// (front-to-back, grouped by pipeline to minimise state changes), transparent
+/// geometry afterwards (back-to-front so alpha blending composites correctly).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Opaque,
+    Transparent,
+}
+
+/// A single queued draw. Holds a borrow of the [`Primitive`] plus the transforms
+/// needed to position it and the camera distance used for sorting.
+struct DrawItem<'a> {
+    primitive: &'a Primitive,
+    model_matrix: Matrix4<f32>,
+    /// Squared distance from the camera, used as the phase sort key.
+    distance: f32,
+}
+
+/// Tracks the pipeline and bind group currently bound on a [`wgpu::RenderPass`]
+/// so redundant `set_pipeline`/`set_bind_group` calls can be skipped while a
+/// phase is recorded. Identity is compared by [`Arc`] pointer.
+#[derive(Default)]
+pub struct BoundState {
+    shader: Option<*const PbrShader>,
+    material: Option<*const Material>,
+}
+
+impl BoundState {
+    /// Returns `true` if `shader` differs from the currently bound one, updating
+    /// the tracked state to `shader`.
+    pub fn bind_shader(&mut self, shader: &Arc<PbrShader>) -> bool {
+        let ptr = Arc::as_ptr(shader);
+        if self.shader == Some(ptr) {
+            return false;
+        }
+        self.shader = Some(ptr);
+        true
+    }
+
+    /// Returns `true` if `material` differs from the currently bound one,
+    /// updating the tracked state to `material`.
+    pub fn bind_material(&mut self, material: &Arc<Material>) -> bool {
+        let ptr = Arc::as_ptr(material);
+        if self.material == Some(ptr) {
+            return false;
+        }
+        self.material = Some(ptr);
+        true
+    }
+}
+
+/// A `wgpu::Buffer` holding per-[`Instance`] rows that grows on demand. It keeps
+/// its current capacity (in instances) so repeated frames only reallocate when a
+/// batch outgrows the previous high-water mark.
+pub struct InstanceBuffer {
+    buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    /// Creates an empty buffer with room for `capacity` instances.
+    pub fn new(capacity: usize, w_info: &WgpuInfo) -> Self {
+        let buffer = w_info.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("instance buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Uploads `instances`, reallocating the backing buffer if it is too small.
+    pub fn upload(&mut self, instances: &[Instance], w_info: &WgpuInfo) {
+        if instances.len() > self.capacity {
+            // Grow to the next power of two and size the allocation to match, so
+            // `capacity` never over-reports the real buffer and a later frame
+            // between the old and new counts still takes a safe `write_buffer`.
+            self.capacity = instances.len().next_power_of_two();
+            self.buffer = w_info.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("instance buffer"),
+                size: (self.capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        w_info
+            .queue
+            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+}
+
+/// Key identifying draws that can be merged into one instanced call: identical
+/// shader, material and vertex/index buffers.
+#[derive(PartialEq, Eq, Hash)]
+struct BatchKey {
+    shader: *const PbrShader,
+    material: *const Material,
+    vertices: wgpu::Id<wgpu::Buffer>,
+    indices: Option<wgpu::Id<wgpu::Buffer>>,
+}
+
+impl BatchKey {
+    fn of(primitive: &Primitive) -> Self {
+        Self {
+            shader: Arc::as_ptr(&primitive.pbr_shader),
+            material: Arc::as_ptr(&primitive.material),
+            vertices: primitive
+                .vertices
+                .as_ref()
+                .expect("primitive has no vertex buffer")
+                .global_id(),
+            indices: primitive.indices.as_ref().map(|b| b.global_id()),
+        }
+    }
+}
+
+/// Groups draw requests sharing a shader+material+buffers and flushes them as a
+/// single instanced `draw_indexed` each, dramatically cutting draw-call count
+/// for scenes with many repeated meshes.
+#[derive(Default)]
+pub struct InstanceBatcher<'a> {
+    order: Vec<BatchKey>,
+    batches: HashMap<BatchKey, (&'a Primitive, Vec<Instance>)>,
+}
+
+impl<'a> InstanceBatcher<'a> {
+    pub fn new() -> Self {
+        Self {
+            order: Vec::new(),
+            batches: HashMap::new(),
+        }
+    }
+
+    /// Queues `primitive` at `model_matrix`, appending its [`Instance`] row to the
+    /// matching batch (creating one on first sight).
+    pub fn add(&mut self, primitive: &'a Primitive, model_matrix: &Matrix4<f32>) {
+        let key = BatchKey::of(primitive);
+        match self.batches.get_mut(&key) {
+            Some((_, instances)) => instances.push(Instance::new(model_matrix)),
+            None => {
+                self.order.push(BatchKey::of(primitive));
+                self.batches
+                    .insert(key, (primitive, vec![Instance::new(model_matrix)]));
+            }
+        }
+    }
+
+    /// Uploads every batch into `instance_buffer` back-to-back and records one
+    /// instanced draw per batch, reusing a [`BoundState`] so batches with the
+    /// same shader/material skip redundant binds.
+    pub fn flush<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        instance_buffer: &'pass mut InstanceBuffer,
+        w_info: &WgpuInfo,
+    ) where
+        'a: 'pass,
+    {
+        // Concatenate all instance rows so the whole frame lives in one buffer;
+        // each batch is then drawn from its slice via a base-instance offset.
+        let mut all: Vec<Instance> = Vec::new();
+        let mut spans: Vec<(&Primitive, u32, u32)> = Vec::new();
+        for key in &self.order {
+            let (primitive, instances) = &self.batches[key];
+            let start = all.len() as u32;
+            all.extend_from_slice(instances);
+            spans.push((primitive, start, instances.len() as u32));
+        }
+        instance_buffer.upload(&all, w_info);
+
+        let mut state = BoundState::default();
+        for (primitive, start, count) in spans {
+            if state.bind_shader(&primitive.pbr_shader) {
+                render_pass.set_pipeline(&primitive.pbr_shader.pipeline);
+                render_pass.set_bind_group(0, &primitive.pbr_shader.uniforms.bind_group, &[]);
+            }
+            if state.bind_material(&primitive.material) {
+                render_pass.set_bind_group(1, &primitive.material.bind_group, &[]);
+            }
+
+            let vertices = match primitive.skin.as_ref() {
+                Some(skin) => &skin.output,
+                None => primitive.vertices.as_ref().unwrap(),
+            };
+            render_pass.set_vertex_buffer(0, vertices.slice(..));
+            render_pass.set_vertex_buffer(1, instance_buffer.buffer().slice(..));
+
+            if let Some(indices) = primitive.indices.as_ref() {
+                render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..primitive.num_indices, 0, start..start + count);
+            } else {
+                render_pass.draw(0..primitive.num_vertices, start..start + count);
+            }
+        }
+    }
+}
+
+/// Collects the visible [`Primitive`]s of a frame, buckets them into phases and
+/// records them to a [`wgpu::RenderPass`] in the correct order.
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    opaque: Vec<DrawItem<'a>>,
+    transparent: Vec<DrawItem<'a>>,
+    /// Parallel-recording toggle, `false` by default. Neither
+    /// [`flush`](Self::flush) nor [`flush_parallel`](Self::flush_parallel) reads
+    /// it: `flush` always records on the calling thread and `flush_parallel`
+    /// always records across rayon workers into render bundles.
+    pub parallel: bool,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self {
+            opaque: Vec::new(),
+            transparent: Vec::new(),
+            parallel: false,
+        }
+    }
+
+    /// Clears the collected draws, keeping the allocated capacity for reuse.
+    pub fn clear(&mut self) {
+        self.opaque.clear();
+        self.transparent.clear();
+    }
+
+    /// Queues `primitive` for this frame, picking its phase from the material's
+    /// alpha mode and computing its camera distance for sorting.
+    pub fn add(
+        &mut self,
+        primitive: &'a Primitive,
+        model_matrix: &Matrix4<f32>,
+        camera_position: &Vector3<f32>,
+    ) {
+        let origin = model_matrix.transform_point(cgmath::Point3::new(0.0, 0.0, 0.0));
+        let distance = (Vector3::new(origin.x, origin.y, origin.z) - camera_position).magnitude2();
+
+        let item = DrawItem {
+            primitive,
+            model_matrix: *model_matrix,
+            distance,
+        };
+
+        match primitive.phase() {
+            Phase::Opaque => self.opaque.push(item),
+            Phase::Transparent => self.transparent.push(item),
+        }
+    }
+
+    /// Sorts both phases into their final draw order. Opaque primitives are
+    /// grouped by shader to batch pipeline binds and sorted front-to-back within
+    /// each shader; transparent primitives are sorted strictly back-to-front.
+    fn sort(&mut self) {
+        self.opaque.sort_by(|a, b| {
+            (Arc::as_ptr(&a.primitive.pbr_shader))
+                .cmp(&Arc::as_ptr(&b.primitive.pbr_shader))
+                .then(a.distance.total_cmp(&b.distance))
+        });
+        self.transparent
+            .sort_by(|a, b| b.distance.total_cmp(&a.distance));
+    }
+
+    /// Sorts and records both phases to `render_pass` on the calling thread. Each
+    /// draw's transform is written into its own row of `instances` and drawn via a
+    /// base-instance offset, so no two draws share a mutable uniform.
+    pub fn flush<'pass>(
+        &'pass mut self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        instances: &'pass mut InstanceBuffer,
+        w_info: &WgpuInfo,
+    ) where
+        'a: 'pass,
+    {
+        self.sort();
+
+        let rows: Vec<Instance> = self
+            .opaque
+            .iter()
+            .chain(self.transparent.iter())
+            .map(|item| Instance::new(&item.model_matrix))
+            .collect();
+        instances.upload(&rows, w_info);
+
+        let mut state = BoundState::default();
+        for (i, item) in self.opaque.iter().chain(self.transparent.iter()).enumerate() {
+            item.primitive.draw(render_pass, instances.buffer(), i as u32, &mut state);
+        }
+    }
+
+    /// Sorts, records the phases in parallel into [`wgpu::RenderBundle`]s and
+    /// executes them on `render_pass`. Each phase is split into fixed-size chunks
+    /// recorded independently by rayon workers; the finished bundles are collected
+    /// in their original (sorted) order before execution so the opaque-then-
+    /// transparent ordering is preserved exactly.
+    pub fn flush_parallel<'pass>(
+        &'pass mut self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        instances: &'pass mut InstanceBuffer,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        w_info: &WgpuInfo,
+    ) where
+        'a: 'pass,
+    {
+        self.sort();
+
+        // Upload one transform per draw up front; the rayon workers only ever
+        // read the buffer (via a base-instance offset), so the recording is free
+        // of the shared-uniform write race the serial path also avoids.
+        let rows: Vec<Instance> = self
+            .opaque
+            .iter()
+            .chain(self.transparent.iter())
+            .map(|item| Instance::new(&item.model_matrix))
+            .collect();
+        instances.upload(&rows, w_info);
+
+        // Chunk both phases in order; opaque chunks precede transparent ones so
+        // flattening after the parallel map keeps blending correct. `base` is the
+        // global instance index of each chunk's first draw.
+        let mut chunks: Vec<(u32, &[DrawItem<'a>])> = Vec::new();
+        let mut base = 0u32;
+        for chunk in self.opaque.chunks(BUNDLE_CHUNK).chain(self.transparent.chunks(BUNDLE_CHUNK)) {
+            chunks.push((base, chunk));
+            base += chunk.len() as u32;
+        }
+
+        let bundles: Vec<wgpu::RenderBundle> = chunks
+            .par_iter()
+            .map(|(base, chunk)| {
+                Self::record_bundle(chunk, *base, instances.buffer(), color_format, depth_format, w_info)
+            })
+            .collect();
+
+        render_pass.execute_bundles(bundles.iter());
+    }
+
+    /// Records a single chunk of draws into a [`wgpu::RenderBundle`]. The encoder
+    /// lives and dies inside this call, so it never crosses the worker-thread
+    /// boundary; only the finished (thread-safe) bundle is returned.
+    fn record_bundle(
+        chunk: &[DrawItem<'a>],
+        base: u32,
+        instance_buffer: &wgpu::Buffer,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        w_info: &WgpuInfo,
+    ) -> wgpu::RenderBundle {
+        let mut encoder =
+            w_info.device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                label: Some("phase bundle"),
+                color_formats: &[Some(color_format)],
+                depth_stencil: depth_format.map(|format| wgpu::RenderBundleDepthStencil {
+                    format,
+                    depth_read_only: false,
+                    stencil_read_only: true,
+                }),
+                sample_count: 1,
+                multiview: None,
+            });
+
+        let mut state = BoundState::default();
+        for (j, item) in chunk.iter().enumerate() {
+            let prim = item.primitive;
+            let instance = base + j as u32;
+
+            if state.bind_shader(&prim.pbr_shader) {
+                encoder.set_pipeline(&prim.pbr_shader.pipeline);
+                encoder.set_bind_group(0, &prim.pbr_shader.uniforms.bind_group, &[]);
+            }
+            if state.bind_material(&prim.material) {
+                encoder.set_bind_group(1, &prim.material.bind_group, &[]);
+            }
+
+            let vertices = match prim.skin.as_ref() {
+                Some(skin) => &skin.output,
+                None => prim.vertices.as_ref().expect("primitive has no vertex buffer"),
+            };
+            encoder.set_vertex_buffer(0, vertices.slice(..));
+            encoder.set_vertex_buffer(1, instance_buffer.slice(..));
+            if let Some(indices) = prim.indices.as_ref() {
+                encoder.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+                encoder.draw_indexed(0..prim.num_indices, 0, instance..instance + 1);
+            } else {
+                encoder.draw(0..prim.num_vertices, instance..instance + 1);
+            }
+        }
+
+        encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("phase bundle"),
+        })
+    }
+}
@@ -1,7 +1,13 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
 use wgpu::Device;
 
 use crate::{WgpuInfo, uniform::Uniform};
 
+/// Root directory WGSL `#include`s are resolved against.
+const SHADER_ROOT: &str = "crates/tar_assets/src/shaders";
+
 bitflags! {
     /// Flags matching the defines in the PBR shader
     pub struct ShaderFlags: u16 {
@@ -19,6 +25,7 @@ bitflags! {
         const HAS_METALROUGHNESSMAP = 1 << 8;
         const HAS_OCCLUSIONMAP      = 1 << 9;
         const USE_TEX_LOD           = 1 << 10;
+        const USE_SHADOWS           = 1 << 11;
     }
 }
 
@@ -32,48 +39,290 @@ impl ShaderFlags {
     }   
 }
 
+/// Expands `#include "path.wgsl"` directives, resolving each path relative to
+/// `root` and guarding against include cycles via `visited`. `current` is the
+/// file `source` was read from, used to report cyclic includes. A cyclic or
+/// missing/unreadable include is surfaced as [`crate::Error::ShaderPreprocess`]
+/// rather than aborting the process, matching [`apply_conditionals`].
+fn expand_includes(
+    source: &str,
+    root: &Path,
+    current: &Path,
+    visited: &mut HashSet<PathBuf>,
+) -> crate::Result<String> {
+    let mut out = String::with_capacity(source.len());
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("#include") {
+            let path = rest.trim().trim_matches('"');
+            let resolved = root.join(path);
+            if !visited.insert(resolved.clone()) {
+                return Err(crate::Error::ShaderPreprocess(format!(
+                    "cyclic #include of {:?} (from {:?})",
+                    resolved, current
+                )));
+            }
+            let included = std::fs::read_to_string(&resolved).map_err(|e| {
+                crate::Error::ShaderPreprocess(format!(
+                    "failed to #include {:?}: {}",
+                    resolved, e
+                ))
+            })?;
+            out.push_str(&expand_includes(&included, root, &resolved, visited)?);
+            visited.remove(&resolved);
+            out.push('\n');
+        } else {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// One open conditional level: whether the current branch is being emitted and
+/// whether any branch at this level has been taken yet (so `#else` knows whether
+/// to activate).
+struct Conditional {
+    /// `true` while the currently-open branch should be emitted.
+    active: bool,
+    /// `true` once any branch at this level has matched, making later `#else`
+    /// branches inactive.
+    taken: bool,
+    /// Whether the enclosing scope was emitting when this level opened; a nested
+    /// block inside a skipped branch stays skipped regardless of its own guard.
+    parent_active: bool,
+}
+
+/// Evaluates `#define`/`#ifdef`/`#ifndef`/`#else`/`#endif` line by line, emitting
+/// a line only when every enclosing conditional is active. `defines` seeds the
+/// initial define set (from [`ShaderFlags::as_strings`]); `#define` adds to it.
+/// Unmatched `#else`/`#endif` are surfaced as [`crate::Error`].
+fn apply_conditionals(source: &str, defines: &HashSet<String>) -> crate::Result<String> {
+    let mut defines = defines.clone();
+    let mut out = String::with_capacity(source.len());
+    let mut stack: Vec<Conditional> = Vec::new();
+
+    let emitting = |stack: &[Conditional]| stack.last().map_or(true, |c| c.active);
+
+    for (n, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some(name) = directive(trimmed, "#ifdef") {
+            let parent_active = emitting(&stack);
+            let active = parent_active && defines.contains(name);
+            stack.push(Conditional { active, taken: active, parent_active });
+        } else if let Some(name) = directive(trimmed, "#ifndef") {
+            let parent_active = emitting(&stack);
+            let active = parent_active && !defines.contains(name);
+            stack.push(Conditional { active, taken: active, parent_active });
+        } else if trimmed.starts_with("#else") {
+            let level = stack.last_mut().ok_or_else(|| {
+                crate::Error::ShaderPreprocess(format!("#else without #ifdef (line {})", n + 1))
+            })?;
+            level.active = level.parent_active && !level.taken;
+            level.taken |= level.active;
+        } else if trimmed.starts_with("#endif") {
+            stack.pop().ok_or_else(|| {
+                crate::Error::ShaderPreprocess(format!("#endif without #ifdef (line {})", n + 1))
+            })?;
+        } else if let Some(name) = directive(trimmed, "#define") {
+            if emitting(&stack) {
+                defines.insert(name.to_owned());
+            }
+        } else if emitting(&stack) {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    if !stack.is_empty() {
+        return Err(crate::Error::ShaderPreprocess(
+            "unterminated #ifdef/#ifndef".to_owned(),
+        ));
+    }
+
+    Ok(out)
+}
+
+/// Returns the argument of `directive` if `line` is exactly that directive,
+/// trimmed of surrounding whitespace.
+fn directive<'a>(line: &'a str, directive: &str) -> Option<&'a str> {
+    line.strip_prefix(directive)
+        .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        .map(str::trim)
+}
+
+/// Runs the WGSL source through the `#include` and conditional passes, producing
+/// the final feature-specialised module string for `defines`.
+fn preprocess(source: &str, defines: &[String]) -> crate::Result<String> {
+    let root = Path::new(SHADER_ROOT);
+    let mut visited = HashSet::new();
+    let included = expand_includes(source, root, root, &mut visited)?;
+    let defines: HashSet<String> = defines.iter().cloned().collect();
+    apply_conditionals(&included, &defines)
+}
+
 pub struct Shader {
     pub module: wgpu::ShaderModule,
 }
 impl Shader {
-    pub fn from_source(shader_code: &str, defines: &[String], w_info: &WgpuInfo) -> Self {
+    pub fn from_source(shader_code: &str, defines: &[String], w_info: &WgpuInfo) -> crate::Result<Self> {
+        let processed = preprocess(shader_code, defines)?;
         let shader = wgpu::ShaderModuleDescriptor {
             label: Some("pbr shader"),
-            source: wgpu::ShaderSource::Wgsl(shader_code.into()),
+            source: wgpu::ShaderSource::Wgsl(processed.into()),
         };
 
         let module = w_info.device.create_shader_module(shader);
 
-        Self {
+        Ok(Self {
             module
-        }
+        })
     }
 }
 
 
+/// Per-material constants passed to [`PbrShader::new`]. [`PbrUniforms::new`]
+/// accepts them but does not upload them: no uniform buffer holds these values,
+/// and the slice fields are not retained.
+pub struct MaterialInput<'a> {
+    pub base_color_factor: &'a [f32],
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub emissive_factor: &'a [f32],
+    pub alpha_cutoff: f32,
+}
+
 pub struct PbrShader {
     pub shader: Shader,
     pub flags: ShaderFlags,
     pub uniforms: PbrUniforms,
+    /// Render pipeline specialised for `flags`, built against the uniform and
+    /// material bind group layouts.
+    pub pipeline: wgpu::RenderPipeline,
 }
 
 impl PbrShader {
-    pub fn new(flags: ShaderFlags, w_info: &WgpuInfo) -> Self {
-        let mut shader = Shader::from_source(
+    pub fn new(flags: ShaderFlags, material: MaterialInput<'_>, w_info: &WgpuInfo) -> crate::Result<Self> {
+        let shader = Shader::from_source(
             include_str!("shaders/pbr.wgsl"),
             &flags.as_strings(),
-            w_info);
+            w_info)?;
 
-        Self {
+        let uniforms = PbrUniforms::new(material, w_info);
+        let pipeline = uniforms.create_pipeline(&shader, w_info);
+
+        Ok(Self {
             shader,
-            flags
-        }
+            flags,
+            uniforms,
+            pipeline,
+        })
     }
 }
 
 pub struct PbrUniforms {
     pub u_MPVMatrix: Uniform<[[f32; 4]; 4]>,
     pub u_ModelMatrix: Uniform<[[f32; 4]; 4]>,
-    pub u_Camera
+    pub u_Camera: Uniform<[f32; 3]>,
+    /// Light view-projection used to project fragments into shadow space. Only
+    /// meaningful when [`ShaderFlags::USE_SHADOWS`] is set.
+    pub u_LightViewProj: Uniform<[[f32; 4]; 4]>,
+    /// Per-light shadow filtering parameters and the Poisson-disc sample table.
+    pub u_Shadow: Uniform<crate::shadow::ShadowUniform>,
+    /// Bind group holding every uniform buffer above, bound at group 0 of the
+    /// PBR pipeline.
+    pub bind_group: wgpu::BindGroup,
+    layout: wgpu::BindGroupLayout,
+}
 
+impl PbrUniforms {
+    /// Allocates the uniform buffers and assembles the group-0 bind group.
+    /// `material` is accepted but its values are not uploaded.
+    pub fn new(material: MaterialInput<'_>, w_info: &WgpuInfo) -> Self {
+        let identity = cgmath::Matrix4::from_scale(1.0).into();
+        let u_MPVMatrix = Uniform::new("u_MPVMatrix", identity, w_info);
+        let u_ModelMatrix = Uniform::new("u_ModelMatrix", identity, w_info);
+        let u_Camera = Uniform::new("u_Camera", [0.0; 3], w_info);
+        let u_LightViewProj = Uniform::new("u_LightViewProj", identity, w_info);
+        let u_Shadow = Uniform::new(
+            "u_Shadow",
+            <crate::shadow::ShadowUniform as bytemuck::Zeroable>::zeroed(),
+            w_info,
+        );
+        let _ = material;
+
+        let entries = [
+            u_MPVMatrix.layout_entry(0),
+            u_ModelMatrix.layout_entry(1),
+            u_Camera.layout_entry(2),
+            u_LightViewProj.layout_entry(3),
+            u_Shadow.layout_entry(4),
+        ];
+        let layout = w_info.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("pbr uniforms"),
+            entries: &entries,
+        });
+        let bind_group = w_info.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("pbr uniforms"),
+            layout: &layout,
+            entries: &[
+                u_MPVMatrix.binding(0),
+                u_ModelMatrix.binding(1),
+                u_Camera.binding(2),
+                u_LightViewProj.binding(3),
+                u_Shadow.binding(4),
+            ],
+        });
+
+        Self {
+            u_MPVMatrix,
+            u_ModelMatrix,
+            u_Camera,
+            u_LightViewProj,
+            u_Shadow,
+            bind_group,
+            layout,
+        }
+    }
+
+    /// Uploads `model` and its combined `mvp` for the next draw.
+    pub fn update(&self, model_matrix: &cgmath::Matrix4<f32>, mvp_matrix: &cgmath::Matrix4<f32>) {
+        self.u_ModelMatrix.set((*model_matrix).into());
+        self.u_MPVMatrix.set((*mvp_matrix).into());
+    }
+
+    /// Builds the PBR render pipeline for `shader`, binding the uniform group at
+    /// 0 and taking static [`Vertex`](crate::primitive::Vertex) plus per-instance
+    /// [`Instance`](crate::primitive::Instance) buffers.
+    fn create_pipeline(&self, shader: &Shader, w_info: &WgpuInfo) -> wgpu::RenderPipeline {
+        let layout = w_info.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pbr pipeline"),
+            bind_group_layouts: &[&self.layout],
+            push_constant_ranges: &[],
+        });
+        w_info.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pbr pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: "vs_main",
+                buffers: &[
+                    crate::primitive::Vertex::desc(),
+                    crate::primitive::Instance::desc(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader.module,
+                entry_point: "fs_main",
+                targets: &[Some(w_info.surface_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
 }
\ No newline at end of file
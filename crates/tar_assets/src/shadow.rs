@@ -0,0 +1,275 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::Matrix4;
+
+use crate::WgpuInfo;
+
+/// Maximum number of Poisson-disc samples uploaded to the shader. The table is
+/// fixed size on the GPU; unused entries are zero.
+pub const MAX_POISSON_SAMPLES: usize = 64;
+
+/// Depth format used for shadow maps. A plain depth texture sampled through a
+/// comparison sampler for hardware PCF.
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// How a light's shadow map is filtered when sampled during the PBR pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShadowFilter {
+    /// A single raw depth comparison, hard edges.
+    None,
+    /// One bilinear hardware comparison sample (2x2 percentage-closer).
+    Hardware2x2,
+    /// Average of `samples` poisson-disc comparison samples within `radius`.
+    Pcf { samples: u32, radius: f32 },
+    /// Blocker search to estimate penumbra width, then PCF scaled by it.
+    Pcss {
+        samples: u32,
+        radius: f32,
+        /// Size of the area light, drives penumbra growth.
+        light_size: f32,
+    },
+}
+
+impl Default for ShadowFilter {
+    fn default() -> Self {
+        ShadowFilter::Hardware2x2
+    }
+}
+
+impl ShadowFilter {
+    /// Number of poisson-disc samples this mode draws from the precomputed table,
+    /// or `0` for modes that take a single sample.
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            ShadowFilter::None | ShadowFilter::Hardware2x2 => 0,
+            ShadowFilter::Pcf { samples, .. } | ShadowFilter::Pcss { samples, .. } => *samples,
+        }
+    }
+
+    /// Discriminant matching the `filter` branch in `pbr.wgsl`.
+    fn mode_index(&self) -> u32 {
+        match self {
+            ShadowFilter::None => 0,
+            ShadowFilter::Hardware2x2 => 1,
+            ShadowFilter::Pcf { .. } => 2,
+            ShadowFilter::Pcss { .. } => 3,
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match self {
+            ShadowFilter::Pcf { radius, .. } | ShadowFilter::Pcss { radius, .. } => *radius,
+            _ => 0.0,
+        }
+    }
+
+    fn light_size(&self) -> f32 {
+        match self {
+            ShadowFilter::Pcss { light_size, .. } => *light_size,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Per-light shadow settings, including the filter mode and the depth bias used
+/// to fight shadow acne.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowConfig {
+    pub filter: ShadowFilter,
+    /// Depth offset applied during the compare to combat self-shadowing acne.
+    pub depth_bias: f32,
+    /// Square resolution of the depth texture.
+    pub resolution: u32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            filter: ShadowFilter::default(),
+            depth_bias: 0.005,
+            resolution: 2048,
+        }
+    }
+}
+
+/// A precomputed poisson-disc sample table. Regenerated only when the requested
+/// sample count changes, since generation is comparatively expensive.
+#[derive(Debug, Default)]
+pub struct PoissonDisc {
+    samples: Vec<[f32; 2]>,
+}
+
+impl PoissonDisc {
+    /// Returns the sample table for `count`, regenerating it in place if the
+    /// current table was built for a different count.
+    pub fn get(&mut self, count: u32) -> &[[f32; 2]] {
+        if self.samples.len() != count as usize {
+            self.samples = Self::generate(count);
+        }
+        &self.samples
+    }
+
+    /// Builds `count` points spread over the unit disc using a Vogel spiral,
+    /// which gives an even, poisson-like distribution without an RNG.
+    fn generate(count: u32) -> Vec<[f32; 2]> {
+        // Golden-angle increment for the sunflower/Vogel spiral.
+        const GOLDEN_ANGLE: f32 = 2.399_963_2;
+        (0..count)
+            .map(|i| {
+                let r = ((i as f32 + 0.5) / count as f32).sqrt();
+                let theta = i as f32 * GOLDEN_ANGLE;
+                [r * theta.cos(), r * theta.sin()]
+            })
+            .collect()
+    }
+
+    /// Copies the first [`MAX_POISSON_SAMPLES`] samples into a GPU-friendly fixed
+    /// array of `vec4`s (the `.zw` lanes are padding for std140 alignment).
+    fn table(&mut self, count: u32) -> [[f32; 4]; MAX_POISSON_SAMPLES] {
+        let count = count.min(MAX_POISSON_SAMPLES as u32);
+        let samples = self.get(count);
+        let mut table = [[0.0; 4]; MAX_POISSON_SAMPLES];
+        for (dst, src) in table.iter_mut().zip(samples) {
+            dst[0] = src[0];
+            dst[1] = src[1];
+        }
+        table
+    }
+}
+
+/// GPU-side per-light shadow parameters uploaded to [`PbrUniforms::u_Shadow`].
+/// Field order and padding follow std140 so it maps directly onto the WGSL
+/// struct in `pbr.wgsl`.
+///
+/// [`PbrUniforms::u_Shadow`]: crate::shader::PbrUniforms
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct ShadowUniform {
+    pub light_view_proj: [[f32; 4]; 4],
+    pub poisson_disc: [[f32; 4]; MAX_POISSON_SAMPLES],
+    /// 0=None, 1=Hardware2x2, 2=PCF, 3=PCSS.
+    pub filter_mode: u32,
+    pub sample_count: u32,
+    pub kernel_radius: f32,
+    pub depth_bias: f32,
+    pub light_size: f32,
+    pub _pad: [f32; 3],
+}
+
+impl ShadowUniform {
+    /// Builds the uniform for `map`, drawing the sample table from `disc`.
+    pub fn new(map: &ShadowMap, disc: &mut PoissonDisc) -> Self {
+        let filter = map.config.filter;
+        let sample_count = filter.sample_count();
+        Self {
+            light_view_proj: map.light_view_proj.into(),
+            poisson_disc: disc.table(sample_count),
+            filter_mode: filter.mode_index(),
+            sample_count,
+            kernel_radius: filter.radius(),
+            depth_bias: map.config.depth_bias,
+            light_size: filter.light_size(),
+            _pad: [0.0; 3],
+        }
+    }
+}
+
+/// A single light's shadow map: the offscreen depth texture rendered from the
+/// light's point of view plus the comparison sampler used to read it back.
+pub struct ShadowMap {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    /// Light view-projection, used both to render depth and to project fragments
+    /// into light space during shading.
+    pub light_view_proj: Matrix4<f32>,
+    pub config: ShadowConfig,
+}
+
+impl ShadowMap {
+    pub fn new(config: ShadowConfig, w_info: &WgpuInfo) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.resolution,
+            height: config.resolution,
+            depth_or_array_layers: 1,
+        };
+        let texture = w_info.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow map"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // `Hardware2x2`/`Pcf`/`Pcss` all rely on a comparison sampler; `None`
+        // still uses it, just with a single tap.
+        let sampler = w_info.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow comparison sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+            light_view_proj: Matrix4::from_scale(1.0),
+            config,
+        }
+    }
+
+    /// Bind group layout entries contributed by a shadow map: the depth texture
+    /// at `base` and its comparison sampler at `base + 1`. The PBR uniform bind
+    /// group built by [`PbrUniforms::new`](crate::shader::PbrUniforms::new) does
+    /// not include them.
+    pub fn bind_group_layout_entries(base: u32) -> [wgpu::BindGroupLayoutEntry; 2] {
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: base,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: base + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+        ]
+    }
+
+    /// Begins a depth-only render pass targeting this shadow map, into which the
+    /// caller records the scene from the light's view-projection.
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shadow pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use cgmath::Matrix4;
+use wgpu::util::DeviceExt;
+
+use crate::WgpuInfo;
+
+/// Number of vertices handled per compute workgroup. Must match the
+/// `@workgroup_size` in `skin.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// A compute pipeline plus its layout, the skinning counterpart to the render
+/// pipeline held by [`PbrShader`](crate::shader::PbrShader). One instance is
+/// shared by every skinned [`Primitive`](crate::primitive::Primitive).
+pub struct SkinPipeline {
+    pub layout: wgpu::PipelineLayout,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub pipeline: wgpu::ComputePipeline,
+}
+
+impl SkinPipeline {
+    pub fn new(w_info: &WgpuInfo) -> Self {
+        let module = w_info.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("skinning shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/skin.wgsl").into()),
+        });
+
+        // bindings: joint matrices (storage), source vertices (storage, read),
+        // output vertices (storage, read-write).
+        let bind_group_layout =
+            w_info.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("skinning bind group layout"),
+                entries: &[
+                    storage_entry(0, true),
+                    storage_entry(1, true),
+                    storage_entry(2, false),
+                ],
+            });
+
+        let layout = w_info.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("skinning pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = w_info.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("skinning pipeline"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: "main",
+        });
+
+        Self {
+            layout,
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Per-primitive skinning resources: the joint-matrix storage buffer refreshed
+/// each frame, the output vertex buffer the render pass binds instead of the
+/// bind-pose vertices, and the compute bind group tying them together.
+pub struct Skin {
+    queue: Arc<wgpu::Queue>,
+    joint_buffer: wgpu::Buffer,
+    pub output: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    num_vertices: u32,
+}
+
+impl Skin {
+    /// Allocates the joint and output buffers for a primitive with `num_vertices`
+    /// vertices, binding them against `pipeline`. `source` is the static bind-pose
+    /// vertex buffer; `vertex_size` is its per-vertex stride in bytes.
+    pub fn new(
+        num_vertices: u32,
+        vertex_size: u64,
+        max_joints: usize,
+        source: &wgpu::Buffer,
+        pipeline: &SkinPipeline,
+        w_info: &WgpuInfo,
+    ) -> Self {
+        let joint_buffer = w_info.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("joint matrices"),
+            size: (max_joints * std::mem::size_of::<[[f32; 4]; 4]>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let output = w_info.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("skinned vertices"),
+            size: num_vertices as u64 * vertex_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = w_info.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skinning bind group"),
+            layout: &pipeline.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: joint_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: source.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            queue: Arc::clone(&w_info.queue),
+            joint_buffer,
+            output,
+            bind_group,
+            num_vertices,
+        }
+    }
+
+    /// Uploads `joint_matrices` and records the skinning dispatch into
+    /// `compute_pass`. After submission [`output`](Self::output) holds the
+    /// transformed positions/normals/tangents for this frame.
+    pub fn dispatch<'a>(
+        &'a self,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+        joint_matrices: &[Matrix4<f32>],
+    ) {
+        let raw: Vec<[[f32; 4]; 4]> = joint_matrices.iter().map(|m| (*m).into()).collect();
+        self.queue
+            .write_buffer(&self.joint_buffer, 0, bytemuck::cast_slice(&raw));
+
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        let workgroups = (self.num_vertices + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+        compute_pass.dispatch_workgroups(workgroups, 1, 1);
+    }
+}
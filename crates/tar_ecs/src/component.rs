@@ -9,7 +9,8 @@ use std::{
 use crate::{
     store::sparse::SparseSetIndex,
     bundle::Bundle,
-    archetype::{ Archetypes, ArchetypeId }
+    archetype::{ Archetypes, ArchetypeId },
+    entity::Entity
 };
 
 /// A [`Component`] is nothing more but data, which can be stored in a given
@@ -17,7 +18,28 @@ use crate::{
 /// manually be implemented on a type, or via `#[derive(Component)]`.
 ///
 /// Read further: [`Bundle`]
-pub trait Component: Send + Sync + 'static {}
+pub trait Component: Send + Sync + 'static {
+    /// Where this [`Component`] is stored. Defaults to [`StorageType::Table`];
+    /// override it on components that are added and removed frequently to avoid
+    /// an archetype move on every toggle (see [`StorageType`]).
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+}
+
+
+/// How a [`Component`] is laid out in a [`World`](crate::world::World).
+///
+/// - [`Table`](StorageType::Table): stored in the archetype's tables, fast to
+///   iterate but adding/removing it migrates the whole entity to another
+///   archetype, copying all of its components.
+/// - [`SparseSet`](StorageType::SparseSet): stored in a separate store keyed by
+///   entity, so adding/removing only touches that entity's slot and triggers no
+///   archetype move — the right tradeoff for churny components.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum StorageType {
+    #[default]
+    Table,
+    SparseSet
+}
 
 
 /// Every [`Component`] gets its own [`ComponentId`] per [`World`](crate::world::World). This
@@ -58,6 +80,7 @@ impl SparseSetIndex for ComponentId {
 pub struct ComponentDescription {
     name: &'static str,
     send_sync: bool,
+    storage: StorageType,
     type_id: Option<TypeId>,
     layout: Layout,
     drop: Option<unsafe fn(*mut u8)>
@@ -68,6 +91,7 @@ impl std::fmt::Debug for ComponentDescription {
         f.debug_struct("ComponentDescriptor")
             .field("name", &self.name)
             .field("send_sync", &self.send_sync)
+            .field("storage", &self.storage)
             .field("type_id", &self.type_id)
             .field("layout", &self.layout)
             .field("drop", &match self.drop {
@@ -92,6 +116,7 @@ impl ComponentDescription {
         Self {
             name: type_name::<T>(),
             send_sync: true,
+            storage: T::STORAGE_TYPE,
             type_id: Some(TypeId::of::<T>()),
             layout: Layout::new::<T>(),
             drop: needs_drop::<T>().then_some(Self::drop_ptr::<T>)
@@ -103,12 +128,14 @@ impl ComponentDescription {
     /// - type must be `Send + Sync`
     pub unsafe fn new_raw(
         name: impl Into<&'static str>,
+        storage: StorageType,
         layout: Layout,
         drop: Option<unsafe fn(*mut u8)>
     ) -> Self {
         Self {
             name: name.into(),
             send_sync: true,
+            storage,
             type_id: None,
             layout,
             drop
@@ -119,6 +146,7 @@ impl ComponentDescription {
         Self {
             name: type_name::<T>(),
             send_sync: false,
+            storage: StorageType::Table,
             type_id: Some(TypeId::of::<T>()),
             layout: Layout::new::<T>(),
             drop: needs_drop::<T>().then_some(Self::drop_ptr::<T>)
@@ -135,6 +163,12 @@ impl ComponentDescription {
         self.send_sync
     }
 
+    /// The [`StorageType`] this [`Component`] is routed into.
+    #[inline]
+    pub fn storage(&self) -> StorageType {
+        self.storage
+    }
+
     #[inline]
     pub fn type_id(&self) -> Option<TypeId> {
         self.type_id
@@ -223,13 +257,196 @@ impl Components {
 }
 
 
+/// A per-[`Entity`] store for a single [`StorageType::SparseSet`]
+/// [`Component`]. Component bytes live in a packed `dense` buffer; `sparse` maps
+/// an entity index to its dense slot (`0` meaning "absent", slots are stored
+/// biased by one). Because it is keyed by entity rather than folded into an
+/// archetype table, adding or removing the component only touches that entity's
+/// slot and never migrates it to another archetype — the whole point of
+/// [`StorageType::SparseSet`].
+///
+/// [`World`](crate::world::World) owns one of these per sparse component (see
+/// [`SparseStores`]) and routes inserts/removes here instead of through an
+/// archetype move.
+pub struct SparseStore {
+    layout: Layout,
+    drop: Option<unsafe fn(*mut u8)>,
+    item_size: usize,
+    dense: Vec<u8>,
+    /// entity index -> dense slot + 1 (`0` = absent).
+    sparse: Vec<u32>,
+    /// dense slot -> owning entity index, kept in sync for swap-removal.
+    entities: Vec<u32>
+}
+
+impl SparseStore {
+    /// New, empty store for the component described by `description`.
+    pub fn new(description: &ComponentDescription) -> Self {
+        Self {
+            layout: description.layout(),
+            drop: description.drop(),
+            item_size: description.layout().size(),
+            dense: Vec::new(),
+            sparse: Vec::new(),
+            entities: Vec::new()
+        }
+    }
+
+    fn slot(&self, entity_index: usize) -> Option<usize> {
+        match self.sparse.get(entity_index).copied() {
+            Some(dense) if dense != 0 => Some(dense as usize - 1),
+            _ => None
+        }
+    }
+
+    /// Pointer to `entity`'s component, or `None` if it has none.
+    pub fn get(&self, entity_index: usize) -> Option<*mut u8> {
+        let slot = self.slot(entity_index)?;
+        // Zero-sized components keep no bytes but are still "present".
+        if self.item_size == 0 {
+            return Some(self.dense.as_ptr() as *mut u8);
+        }
+        Some(unsafe { self.dense.as_ptr().add(slot * self.item_size) as *mut u8 })
+    }
+
+    /// Writes `value` (owned, of this store's type) into `entity`'s slot,
+    /// overwriting and dropping any previous value.
+    ///
+    /// SAFETY:
+    /// - `value` must point to an initialised value matching [`Self::layout`].
+    pub unsafe fn insert(&mut self, entity_index: usize, value: *const u8) {
+        if self.sparse.len() <= entity_index {
+            self.sparse.resize(entity_index + 1, 0);
+        }
+
+        if let Some(slot) = self.slot(entity_index) {
+            let dst = self.dense.as_mut_ptr().add(slot * self.item_size);
+            if let Some(drop) = self.drop {
+                drop(dst);
+            }
+            std::ptr::copy_nonoverlapping(value, dst, self.item_size);
+            return;
+        }
+
+        let slot = self.entities.len();
+        self.dense.resize((slot + 1) * self.item_size, 0);
+        let dst = self.dense.as_mut_ptr().add(slot * self.item_size);
+        std::ptr::copy_nonoverlapping(value, dst, self.item_size);
+        self.entities.push(entity_index as u32);
+        self.sparse[entity_index] = slot as u32 + 1;
+    }
+
+    /// Removes `entity`'s component, dropping it if present. The last dense slot
+    /// is swapped into the hole so the buffer stays packed.
+    pub fn remove(&mut self, entity_index: usize) {
+        let Some(slot) = self.slot(entity_index) else { return };
+
+        unsafe {
+            let removed = self.dense.as_mut_ptr().add(slot * self.item_size);
+            if let Some(drop) = self.drop {
+                drop(removed);
+            }
+
+            let last = self.entities.len() - 1;
+            if slot != last {
+                let src = self.dense.as_ptr().add(last * self.item_size);
+                std::ptr::copy_nonoverlapping(src, removed, self.item_size);
+                let moved_entity = self.entities[last];
+                self.entities[slot] = moved_entity;
+                self.sparse[moved_entity as usize] = slot as u32 + 1;
+            }
+        }
+
+        self.dense.truncate((self.entities.len() - 1) * self.item_size);
+        self.entities.pop();
+        self.sparse[entity_index] = 0;
+    }
+
+    /// The [`Layout`] of the stored component.
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Number of entities currently holding this component.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// The entity indices currently holding this component, in dense order. Used
+    /// to drive iteration over sparse components, which register no archetype and
+    /// so cannot be reached through the archetype index.
+    #[inline]
+    pub fn entity_indices(&self) -> &[u32] {
+        &self.entities
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+impl Drop for SparseStore {
+    fn drop(&mut self) {
+        if let Some(drop) = self.drop {
+            for slot in 0..self.entities.len() {
+                unsafe { drop(self.dense.as_mut_ptr().add(slot * self.item_size)) }
+            }
+        }
+    }
+}
+
+/// The set of [`SparseStore`]s in a [`World`](crate::world::World), one per
+/// [`StorageType::SparseSet`] [`Component`], indexed by [`ComponentId`]. Table
+/// components have no entry.
+#[derive(Default)]
+pub struct SparseStores {
+    stores: HashMap<ComponentId, SparseStore>
+}
+
+impl SparseStores {
+    #[inline]
+    pub fn new() -> Self {
+        Self { stores: HashMap::new() }
+    }
+
+    /// Returns the store for `id`, creating it from `components` the first time a
+    /// sparse component is seen.
+    pub fn get_or_init(&mut self, id: ComponentId, components: &Components) -> &mut SparseStore {
+        self.stores.entry(id).or_insert_with(|| {
+            let description = components
+                .get_description(id)
+                .expect("component id has no description");
+            SparseStore::new(description)
+        })
+    }
+
+    #[inline]
+    pub fn get(&self, id: ComponentId) -> Option<&SparseStore> {
+        self.stores.get(&id)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, id: ComponentId) -> Option<&mut SparseStore> {
+        self.stores.get_mut(&id)
+    }
+}
+
+
 /// An [`Iterator`] for a given [`Bundle`], which iterates over all
 /// [`Archetype`](crate::archetype::Archetype)s of a [`World`](crate::world::World) who contain the
 /// [`Bundle`].
 pub struct ComponentQuery<'a, T: Bundle<'a>> {
     archetypes: &'a Archetypes,
     archetype_ids: Vec<ArchetypeId>,
+    sparse: &'a SparseStores,
     components: &'a Components,
+    /// Fetched/`With` [`StorageType::SparseSet`] ids a row's entity must hold.
+    required_sparse: Vec<ComponentId>,
+    /// `Without` sparse ids a row's entity must not hold.
+    excluded_sparse: Vec<ComponentId>,
     current: usize,
     index: usize,
     marker: PhantomData<&'a T>
@@ -239,12 +456,56 @@ impl<'a, T: Bundle<'a>> ComponentQuery<'a, T> {
     pub fn new(
         archetype_ids: Vec<ArchetypeId>,
         archetypes: &'a Archetypes,
+        sparse: &'a SparseStores,
         components: &'a Components
     ) -> Self {
         Self {
             archetypes,
             archetype_ids,
+            sparse,
             components,
+            required_sparse: Vec::new(),
+            excluded_sparse: Vec::new(),
+            current: 0,
+            index: 0,
+            marker: PhantomData
+        }
+    }
+
+    /// Builds a query over every archetype containing the fetched bundle `T` and
+    /// satisfying `F`'s [`With`]/[`Without`] constraints. The matching archetype
+    /// set is derived here via [`matching_archetypes`], so callers (e.g.
+    /// [`World::query`](crate::world::World)) pass only the bundle and filter
+    /// types instead of a hand-assembled list of [`ArchetypeId`]s.
+    ///
+    /// [`StorageType::SparseSet`] components register no archetypes, so they are
+    /// split out of the required/excluded sets handed to [`matching_archetypes`]
+    /// and checked per-row in [`next`](Self::next) against [`SparseStores`]
+    /// instead. A query whose bundle and `With` filters name no table component
+    /// selects no archetype and so yields nothing.
+    pub fn filtered<F: QueryFilter>(
+        archetypes: &'a Archetypes,
+        sparse: &'a SparseStores,
+        components: &'a mut Components
+    ) -> Self {
+        let mut required = Vec::new();
+        T::component_ids(components, &mut |id| required.push(id));
+        F::required(components, &mut required);
+
+        let mut excluded = Vec::new();
+        F::excluded(components, &mut excluded);
+
+        let (required_table, required_sparse) = split_by_storage(required, components);
+        let (excluded_table, excluded_sparse) = split_by_storage(excluded, components);
+
+        let archetype_ids = matching_archetypes(&required_table, &excluded_table, archetypes);
+        Self {
+            archetypes,
+            archetype_ids,
+            sparse,
+            components,
+            required_sparse,
+            excluded_sparse,
             current: 0,
             index: 0,
             marker: PhantomData
@@ -256,27 +517,46 @@ impl<'a, T: Bundle<'a>> Iterator for ComponentQuery<'a, T> {
     type Item = T::Ref;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(archetype_ids) = self.archetype_ids.get(self.current) {
-            let archetype = self.archetypes.get(*archetype_ids)?;
-            
-            // TODO Make [`Store`] automatically bound check or something
+        // Loop rather than recurse so long runs of filtered-out rows cannot
+        // overflow the stack.
+        while let Some(archetype_id) = self.archetype_ids.get(self.current) {
+            let archetype = self.archetypes.get(*archetype_id)?;
+
             if self.index == archetype.len() {
-                self.current += 1; 
+                self.current += 1;
                 self.index = 0;
-
-                return self.next();
+                continue;
             }
 
             let index = self.index;
             self.index += 1;
-            
+
+            let entity = unsafe { archetype.get_entity_unchecked(index) };
+            let sparse = self.sparse;
+            if !sparse_row_matches(sparse, entity, &self.required_sparse, &self.excluded_sparse) {
+                continue;
+            }
+
+            let components = self.components;
             // SAFETY:
-            // Archetype is parent of `T: Bundle` archetype, value is safe to use
-            return Some(unsafe { archetype.get_unchecked::<T>(self.components, index) });
+            // The archetype stores every required table component of `T` (it was
+            // selected for them); sparse components are resolved from their store
+            // by the row's entity, matching the fetch performed for `DynamicView`.
+            return Some(unsafe {
+                T::from_components::<T>(components, &mut |id| {
+                    match storage_of(components, id) {
+                        StorageType::Table => archetype.get_ptr(id, index).map(|p| p as *const u8),
+                        StorageType::SparseSet => sparse
+                            .get(id)
+                            .and_then(|store| store.get(entity.index()))
+                            .map(|p| p as *const u8)
+                    }
+                })
+            });
         }
-        
+
         None
-    } 
+    }
 }
 
 /// An [`Iterator`] for a given [`Bundle`], which iterates mutably over all
@@ -285,7 +565,12 @@ impl<'a, T: Bundle<'a>> Iterator for ComponentQuery<'a, T> {
 pub struct ComponentQueryMut<'a, T: Bundle<'a>> {
     archetypes: &'a mut Archetypes,
     archetype_ids: Vec<ArchetypeId>,
+    sparse: &'a SparseStores,
     components: &'a Components,
+    /// Fetched/`With` [`StorageType::SparseSet`] ids a row's entity must hold.
+    required_sparse: Vec<ComponentId>,
+    /// `Without` sparse ids a row's entity must not hold.
+    excluded_sparse: Vec<ComponentId>,
     current: usize,
     index: usize,
     marker: PhantomData<&'a mut T>
@@ -295,12 +580,50 @@ impl<'a, T: Bundle<'a>> ComponentQueryMut<'a, T> {
     pub fn new(
         archetype_ids: Vec<ArchetypeId>,
         archetypes: &'a mut Archetypes,
+        sparse: &'a SparseStores,
         components: &'a Components
     ) -> Self {
         Self {
             archetypes,
             archetype_ids,
+            sparse,
             components,
+            required_sparse: Vec::new(),
+            excluded_sparse: Vec::new(),
+            current: 0,
+            index: 0,
+            marker: PhantomData
+        }
+    }
+
+    /// Mutable counterpart to [`ComponentQuery::filtered`]: selects archetypes
+    /// containing the fetched bundle `T` and satisfying `F` via
+    /// [`matching_archetypes`] rather than a caller-supplied [`ArchetypeId`] list.
+    /// Sparse components are split out and resolved per-row from [`SparseStores`]
+    /// exactly as in [`ComponentQuery::filtered`].
+    pub fn filtered<F: QueryFilter>(
+        archetypes: &'a mut Archetypes,
+        sparse: &'a SparseStores,
+        components: &'a mut Components
+    ) -> Self {
+        let mut required = Vec::new();
+        T::component_ids(components, &mut |id| required.push(id));
+        F::required(components, &mut required);
+
+        let mut excluded = Vec::new();
+        F::excluded(components, &mut excluded);
+
+        let (required_table, required_sparse) = split_by_storage(required, components);
+        let (excluded_table, excluded_sparse) = split_by_storage(excluded, components);
+
+        let archetype_ids = matching_archetypes(&required_table, &excluded_table, archetypes);
+        Self {
+            archetypes,
+            archetype_ids,
+            sparse,
+            components,
+            required_sparse,
+            excluded_sparse,
             current: 0,
             index: 0,
             marker: PhantomData
@@ -312,26 +635,584 @@ impl<'a, T: Bundle<'a>> Iterator for ComponentQueryMut<'a, T> {
     type Item = T::MutRef;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(archetype_ids) = self.archetype_ids.get(self.current) {
-            let archetype = self.archetypes.get_mut(*archetype_ids)?;
-            
-            // TODO Make [`Store`] automatically bound check or something
+        // Loop rather than recurse so long runs of filtered-out rows cannot
+        // overflow the stack.
+        while let Some(archetype_id) = self.archetype_ids.get(self.current) {
+            let archetype = self.archetypes.get_mut(*archetype_id)?;
+
             if self.index == archetype.len() {
-                self.current += 1; 
+                self.current += 1;
                 self.index = 0;
+                continue;
+            }
 
-                return self.next();
+            let index = self.index;
+            self.index += 1;
+
+            let entity = unsafe { archetype.get_entity_unchecked(index) };
+            let sparse = self.sparse;
+            if !sparse_row_matches(sparse, entity, &self.required_sparse, &self.excluded_sparse) {
+                continue;
+            }
+
+            let components = self.components;
+            // SAFETY:
+            // The archetype stores every required table component of `T` (it was
+            // selected for them); sparse components are resolved from their store
+            // by the row's entity, matching the fetch performed for `DynamicView`.
+            return Some(unsafe {
+                T::from_components_mut::<T>(components, &mut |id| {
+                    match storage_of(components, id) {
+                        StorageType::Table => archetype.get_ptr(id, index),
+                        StorageType::SparseSet => sparse
+                            .get(id)
+                            .and_then(|store| store.get(entity.index()))
+                    }
+                })
+            });
+        }
+
+        None
+    }
+}
+
+
+
+/// A runtime-typed view over a set of [`Component`]s chosen by [`ComponentId`]
+/// rather than by a compile-time [`Bundle`] type. For every entity of a matching
+/// [`Archetype`](crate::archetype::Archetype) it yields one raw `*mut u8` per
+/// requested id, in the same order as `ids`.
+///
+/// This mirrors [`ComponentQueryMut`]'s archetype walk but without a static
+/// `Bundle`, which scripting and editor tooling need when the component set is
+/// only known at runtime (see the `gui` editor and the planned `reload_scripts`
+/// path). A returned pointer is valid for as long as the borrow of
+/// [`Archetypes`] lives; callers are responsible for casting it back to the
+/// correct type.
+///
+/// [`StorageType::SparseSet`] components are not stored in the archetype tables,
+/// so they are fetched from the [`SparseStores`] keyed by the row's [`Entity`];
+/// archetype selection therefore intersects only the [`StorageType::Table`] ids
+/// and a row is skipped if any requested sparse component is absent for its
+/// entity.
+pub struct DynamicView<'a> {
+    archetypes: &'a mut Archetypes,
+    archetype_ids: Vec<ArchetypeId>,
+    sparse: &'a SparseStores,
+    ids: &'a [ComponentId],
+    /// Cached [`StorageType`] per requested id, parallel to `ids`.
+    storages: Vec<StorageType>,
+    /// When every requested id is sparse there is no archetype to walk, so
+    /// iteration is driven by the entity indices of the rarest requested sparse
+    /// store instead. `None` means archetype-driven iteration.
+    sparse_entities: Option<Vec<u32>>,
+    current: usize,
+    index: usize
+}
+
+impl<'a> DynamicView<'a> {
+    /// Builds a view over every [`Archetype`](crate::archetype::Archetype)
+    /// containing all of the requested table components, reading any sparse
+    /// components from `sparse`. The matching set is computed here from the
+    /// per-[`ComponentId`] archetype index (see [`matching_archetypes`]) so
+    /// callers only pass the component set they want, not a pre-assembled list of
+    /// [`ArchetypeId`]s.
+    pub fn new(
+        archetypes: &'a mut Archetypes,
+        sparse: &'a SparseStores,
+        components: &Components,
+        ids: &'a [ComponentId]
+    ) -> Self {
+        let storages: Vec<StorageType> = ids
+            .iter()
+            .map(|id| components
+                .get_description(*id)
+                .map_or(StorageType::Table, |d| d.storage()))
+            .collect();
+
+        // Only table components live in the archetype index; sparse ones are
+        // matched per-row against their store below.
+        let table_ids: Vec<ComponentId> = ids
+            .iter()
+            .zip(&storages)
+            .filter(|(_, storage)| **storage == StorageType::Table)
+            .map(|(id, _)| *id)
+            .collect();
+
+        // With no table component to anchor the walk, `matching_archetypes` would
+        // intersect nothing and yield an empty set, so an all-sparse request would
+        // see no entities. Drive iteration off the rarest requested sparse store
+        // instead and fetch every (necessarily sparse) id per entity.
+        let (archetype_ids, sparse_entities) = if table_ids.is_empty() {
+            let entities = ids
+                .iter()
+                .zip(&storages)
+                .filter(|(_, storage)| **storage == StorageType::SparseSet)
+                .filter_map(|(id, _)| sparse.get(*id))
+                .min_by_key(|store| store.len())
+                .map(|store| store.entity_indices().to_vec());
+            (Vec::new(), entities)
+        } else {
+            (matching_archetypes(&table_ids, &[], archetypes), None)
+        };
+
+        Self {
+            archetypes,
+            archetype_ids,
+            sparse,
+            ids,
+            storages,
+            sparse_entities,
+            current: 0,
+            index: 0
+        }
+    }
+}
+
+impl<'a> Iterator for DynamicView<'a> {
+    type Item = Vec<*mut u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // All-sparse request: walk the driving store's entities (every requested
+        // id is sparse here, so there is nothing to read from an archetype).
+        if let Some(entities) = &self.sparse_entities {
+            while let Some(&entity_index) = entities.get(self.index) {
+                self.index += 1;
+
+                let mut ptrs = Vec::with_capacity(self.ids.len());
+                for id in self.ids {
+                    match self.sparse.get(*id).and_then(|s| s.get(entity_index as usize)) {
+                        Some(ptr) => ptrs.push(ptr),
+                        // Entity is in the driving store but lacks another
+                        // requested component; it is not a member of the set.
+                        None => break
+                    }
+                }
+
+                if ptrs.len() == self.ids.len() {
+                    return Some(ptrs);
+                }
+            }
+
+            return None;
+        }
+
+        // Skip past any exhausted archetypes in a loop; recursing once per empty
+        // archetype could overflow the stack on worlds with many of them.
+        while let Some(archetype_id) = self.archetype_ids.get(self.current) {
+            let archetype = self.archetypes.get_mut(*archetype_id)?;
+
+            if self.index == archetype.len() {
+                self.current += 1;
+                self.index = 0;
+                continue;
             }
 
             let index = self.index;
             self.index += 1;
-            
+            let entity = unsafe { archetype.get_entity_unchecked(index) };
+
+            let mut ptrs = Vec::with_capacity(self.ids.len());
+            for (id, storage) in self.ids.iter().zip(&self.storages) {
+                let ptr = match storage {
+                    // SAFETY: the archetype was selected because it contains every
+                    // requested table component, so the lookup is in bounds.
+                    StorageType::Table => unsafe { archetype.get_ptr_unchecked(*id, index) },
+                    StorageType::SparseSet => {
+                        match self.sparse.get(*id).and_then(|s| s.get(entity.index())) {
+                            Some(ptr) => ptr,
+                            // The entity lacks this sparse component; it is not a
+                            // member of the requested set, so skip the row.
+                            None => break
+                        }
+                    }
+                };
+                ptrs.push(ptr);
+            }
+
+            if ptrs.len() == self.ids.len() {
+                return Some(ptrs);
+            }
+        }
+
+        None
+    }
+}
+
+/// Fetches the requested [`Component`] set for a single [`Entity`], returning one
+/// raw `*mut u8` per id (or `None` if the entity is missing any of them). This is
+/// the single-entity convenience over [`DynamicView`].
+pub struct DynamicViewOne;
+
+impl DynamicViewOne {
+    pub fn get(
+        entity: Entity,
+        archetypes: &mut Archetypes,
+        sparse: &SparseStores,
+        components: &Components,
+        ids: &[ComponentId]
+    ) -> Option<Vec<*mut u8>> {
+        let (archetype_id, index) = archetypes.location(entity)?;
+        let archetype = archetypes.get_mut(archetype_id)?;
+
+        let mut ptrs = Vec::with_capacity(ids.len());
+        for id in ids {
+            let storage = components
+                .get_description(*id)
+                .map_or(StorageType::Table, |d| d.storage());
+            let ptr = match storage {
+                // `get_ptr` returns `None` for a component this archetype lacks.
+                StorageType::Table => archetype.get_ptr(*id, index)?,
+                StorageType::SparseSet => sparse.get(*id)?.get(entity.index())?
+            };
+            ptrs.push(ptr);
+        }
+        Some(ptrs)
+    }
+}
+
+
+/// A monotonically increasing counter bumped once per [`World`](crate::world::World)
+/// run. Every stored [`Component`] records the tick it was added and the tick it
+/// was last mutated so reactive systems can query only what has changed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Tick(pub u32);
+
+impl Tick {
+    #[inline]
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// Advances the tick, returning the new value. Called by the `World` at the
+    /// start of each run.
+    #[inline]
+    pub fn bump(&mut self) -> Tick {
+        self.0 = self.0.wrapping_add(1);
+        *self
+    }
+}
+
+/// The add/change bookkeeping stored alongside each component in its archetype
+/// column. `added` is stamped on insertion; `changed` is written every time the
+/// component is accessed mutably through a [`Mut`] wrapper.
+#[derive(Clone, Copy, Debug)]
+pub struct ComponentTicks {
+    pub added: Tick,
+    pub changed: Tick
+}
+
+impl ComponentTicks {
+    /// New ticks for a component inserted on `tick`.
+    #[inline]
+    pub fn new(tick: Tick) -> Self {
+        Self { added: tick, changed: tick }
+    }
+
+    /// Whether the component was added since `last_run`.
+    #[inline]
+    pub fn is_added(&self, last_run: Tick) -> bool {
+        self.added > last_run
+    }
+
+    /// Whether the component was added or mutated since `last_run`.
+    #[inline]
+    pub fn is_changed(&self, last_run: Tick) -> bool {
+        self.changed > last_run
+    }
+}
+
+/// A change-tracking mutable borrow yielded by [`ChangeQuery`]. Reading
+/// through [`Deref`](std::ops::Deref) leaves the ticks untouched; any
+/// [`DerefMut`](std::ops::DerefMut) access stamps the current tick into the
+/// component's `changed_tick`, so a later `Changed<T>` query observes the write.
+/// [`ComponentQueryMut`] yields plain bundle references and stamps no ticks.
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    changed: &'a mut Tick,
+    current: Tick
+}
+
+impl<'a, T> Mut<'a, T> {
+    #[inline]
+    pub fn new(value: &'a mut T, changed: &'a mut Tick, current: Tick) -> Self {
+        Self { value, changed, current }
+    }
+}
+
+impl<'a, T> std::ops::Deref for Mut<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T> std::ops::DerefMut for Mut<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        *self.changed = self.current;
+        self.value
+    }
+}
+
+/// A query filter matching a component against a `last_run` tick. Implementors
+/// ([`Added`], [`Changed`]) are consulted by [`ChangeQuery`] to skip rows that
+/// have not changed; [`ComponentQuery`]/[`ComponentQueryMut`] apply no change
+/// filter.
+pub trait ChangeFilter {
+    fn matches(ticks: &ComponentTicks, last_run: Tick) -> bool;
+}
+
+/// Matches entities whose component was added since the query's `last_run` tick.
+pub struct Added<T: Component>(PhantomData<T>);
+
+impl<T: Component> ChangeFilter for Added<T> {
+    #[inline]
+    fn matches(ticks: &ComponentTicks, last_run: Tick) -> bool {
+        ticks.is_added(last_run)
+    }
+}
+
+/// Matches entities whose component was added or mutated since the query's
+/// `last_run` tick.
+pub struct Changed<T: Component>(PhantomData<T>);
+
+impl<T: Component> ChangeFilter for Changed<T> {
+    #[inline]
+    fn matches(ticks: &ComponentTicks, last_run: Tick) -> bool {
+        ticks.is_changed(last_run)
+    }
+}
+
+/// Matches every row regardless of its ticks; the change-detection query uses it
+/// when no [`Added`]/[`Changed`] filter is requested.
+pub struct AnyTick;
+
+impl ChangeFilter for AnyTick {
+    #[inline]
+    fn matches(_ticks: &ComponentTicks, _last_run: Tick) -> bool {
+        true
+    }
+}
+
+/// A change-tracking query over a single [`Component`] `T`, filtered by
+/// [`ChangeFilter`] `F`. It walks the same archetypes as [`ComponentQueryMut`]
+/// but reads each row's [`ComponentTicks`] and skips the ones that do not satisfy
+/// `F` against `last_run`; every row of those archetypes is still visited. Each
+/// yielded [`Mut`] stamps `current` into the row's `changed`
+/// tick only when written through, feeding a later [`Changed<T>`] query.
+///
+/// This is the query-path wiring for [`Added`]/[`Changed`]; a
+/// [`World`](crate::world::World) run bumps its tick counter and hands the old
+/// value in as `last_run` and the new one as `current`.
+pub struct ChangeQuery<'a, T: Component, F: ChangeFilter = AnyTick> {
+    archetypes: &'a mut Archetypes,
+    archetype_ids: Vec<ArchetypeId>,
+    component: ComponentId,
+    last_run: Tick,
+    current: Tick,
+    current_archetype: usize,
+    index: usize,
+    marker: PhantomData<(&'a mut T, F)>
+}
+
+impl<'a, T: Component, F: ChangeFilter> ChangeQuery<'a, T, F> {
+    /// Builds the query, resolving `T`'s [`ComponentId`] and the archetypes
+    /// containing it. `last_run`/`current` bracket the run whose changes are
+    /// being observed.
+    pub fn new(
+        archetypes: &'a mut Archetypes,
+        components: &'a mut Components,
+        last_run: Tick,
+        current: Tick
+    ) -> Self {
+        let component = components.init::<T>();
+        let archetype_ids = matching_archetypes(&[component], &[], archetypes);
+        Self {
+            archetypes,
+            archetype_ids,
+            component,
+            last_run,
+            current,
+            current_archetype: 0,
+            index: 0,
+            marker: PhantomData
+        }
+    }
+}
+
+impl<'a, T: Component, F: ChangeFilter> Iterator for ChangeQuery<'a, T, F> {
+    type Item = Mut<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Loop (not recurse) so large runs of non-matching rows cannot overflow
+        // the stack.
+        while let Some(archetype_id) = self.archetype_ids.get(self.current_archetype) {
+            let archetype = self.archetypes.get_mut(*archetype_id)?;
+
+            if self.index == archetype.len() {
+                self.current_archetype += 1;
+                self.index = 0;
+                continue;
+            }
+
+            let index = self.index;
+            self.index += 1;
+
             // SAFETY:
-            // Archetype is parent of `T: Bundle` archetype, value is safe to use
-            return Some(unsafe { archetype.get_unchecked_mut::<T>(self.components, index) });
+            // The archetype was selected because it stores `self.component`, so
+            // both the value and its ticks are in bounds at `index`. The two
+            // pointers address disjoint storage (column data vs. tick column), so
+            // the `&mut` borrows handed to `Mut` do not alias.
+            let ticks = unsafe { &mut *archetype.get_ticks_ptr_unchecked(self.component, index) };
+            if !F::matches(ticks, self.last_run) {
+                continue;
+            }
+            let value = unsafe { &mut *archetype.get_ptr_unchecked(self.component, index).cast::<T>() };
+
+            return Some(Mut::new(value, &mut ticks.changed, self.current));
         }
-        
+
         None
-    } 
+    }
+}
+
+
+/// A query filter that requires or excludes [`Component`]s which are not part of
+/// the fetched [`Bundle`]. Implemented by [`With`] and [`Without`] (and tuples of
+/// them), it feeds the sets of [`ComponentId`]s a matching
+/// [`Archetype`](crate::archetype::Archetype) must contain / must not contain.
+pub trait QueryFilter {
+    /// Component ids an archetype must contain to match.
+    fn required(components: &mut Components, ids: &mut Vec<ComponentId>);
+    /// Component ids an archetype must not contain to match.
+    fn excluded(components: &mut Components, ids: &mut Vec<ComponentId>);
 }
 
+/// Requires matching entities to have `T`, without fetching it.
+pub struct With<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    #[inline]
+    fn required(components: &mut Components, ids: &mut Vec<ComponentId>) {
+        ids.push(components.init::<T>());
+    }
+
+    #[inline]
+    fn excluded(_components: &mut Components, _ids: &mut Vec<ComponentId>) {}
+}
+
+/// Excludes entities that have `T`.
+pub struct Without<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+    #[inline]
+    fn required(_components: &mut Components, _ids: &mut Vec<ComponentId>) {}
+
+    #[inline]
+    fn excluded(components: &mut Components, ids: &mut Vec<ComponentId>) {
+        ids.push(components.init::<T>());
+    }
+}
+
+impl QueryFilter for () {
+    #[inline]
+    fn required(_components: &mut Components, _ids: &mut Vec<ComponentId>) {}
+
+    #[inline]
+    fn excluded(_components: &mut Components, _ids: &mut Vec<ComponentId>) {}
+}
+
+macro_rules! filter_tuple_impl {
+    ($($f:ident),*) => {
+        impl<$($f: QueryFilter),*> QueryFilter for ($($f,)*) {
+            #[inline]
+            #[allow(unused_variables)]
+            fn required(components: &mut Components, ids: &mut Vec<ComponentId>) {
+                $(<$f as QueryFilter>::required(components, ids);)*
+            }
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn excluded(components: &mut Components, ids: &mut Vec<ComponentId>) {
+                $(<$f as QueryFilter>::excluded(components, ids);)*
+            }
+        }
+    };
+}
+
+filter_tuple_impl!(A);
+filter_tuple_impl!(A, B);
+filter_tuple_impl!(A, B, C);
+filter_tuple_impl!(A, B, C, D);
+filter_tuple_impl!(A, B, C, D, E);
+filter_tuple_impl!(A, B, C, D, E, F);
+filter_tuple_impl!(A, B, C, D, E, F, G);
+filter_tuple_impl!(A, B, C, D, E, F, G, H);
+
+/// The [`StorageType`] of `id`, defaulting to [`StorageType::Table`] for ids
+/// without a registered description (they cannot be sparse).
+#[inline]
+fn storage_of(components: &Components, id: ComponentId) -> StorageType {
+    components
+        .get_description(id)
+        .map_or(StorageType::Table, |d| d.storage())
+}
+
+/// Partitions `ids` into `(table, sparse)` by their [`StorageType`]. Only table
+/// ids drive [`matching_archetypes`]; sparse ids are checked per-row against
+/// [`SparseStores`].
+fn split_by_storage(
+    ids: Vec<ComponentId>,
+    components: &Components
+) -> (Vec<ComponentId>, Vec<ComponentId>) {
+    ids.into_iter()
+        .partition(|id| storage_of(components, *id) == StorageType::Table)
+}
+
+/// Whether `entity` holds every `required` sparse component and none of the
+/// `excluded` ones, consulting `sparse`.
+#[inline]
+fn sparse_row_matches(
+    sparse: &SparseStores,
+    entity: Entity,
+    required: &[ComponentId],
+    excluded: &[ComponentId]
+) -> bool {
+    let present = |id: ComponentId| sparse.get(id).and_then(|s| s.get(entity.index())).is_some();
+    required.iter().all(|id| present(*id)) && !excluded.iter().any(|id| present(*id))
+}
+
+/// Selects exactly the archetypes whose component set is a superset of
+/// `required` (the fetched bundle's components plus any [`With`]) and disjoint
+/// from `excluded` (any [`Without`]). An empty `required` selects no
+/// archetypes.
+///
+/// The set is built by intersecting the per-[`ComponentId`] archetype lists
+/// registered on [`Archetypes`], so iteration stays O(matching rows) instead of
+/// filtering inside [`ComponentQuery::next`].
+pub fn matching_archetypes(
+    required: &[ComponentId],
+    excluded: &[ComponentId],
+    archetypes: &Archetypes
+) -> Vec<ArchetypeId> {
+    // Intersect the archetype lists of every required component, in the order
+    // given; the result is no larger than the rarest component's list.
+    let mut candidates: Option<Vec<ArchetypeId>> = None;
+    for id in required {
+        let list: Vec<ArchetypeId> = archetypes.with_component(*id).collect();
+        candidates = Some(match candidates {
+            None => list,
+            Some(prev) => prev.into_iter().filter(|a| list.contains(a)).collect()
+        });
+    }
+
+    let mut result = candidates.unwrap_or_default();
+    for id in excluded {
+        let list: Vec<ArchetypeId> = archetypes.with_component(*id).collect();
+        result.retain(|a| !list.contains(a));
+    }
+    result
+}
@@ -1,8 +1,50 @@
+use std::sync::Arc;
+
 use wgpu::util::DeviceExt;
 
 pub mod material;
 pub mod texture;
 
+/// A single per-instance transform row, bound at a second vertex slot with
+/// `step_mode: Instance`. A `mat4` occupies four consecutive `vec4` locations.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+}
+
+impl InstanceRaw {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
 pub struct Model {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: Option<wgpu::Buffer>,
@@ -64,4 +106,71 @@ impl Model {
             render_pass.draw(0..self.num_vertices, 0..1);
         }
     }
+
+    /// Draws this model `instances` times, binding `instance_buffer` at the second
+    /// vertex slot and reading one row of it per instance. The model's own vertex/index buffers and material are
+    /// reused unchanged, so one GPU copy backs every placement.
+    pub fn render_instanced<'rps>(
+        &'rps self,
+        render_pass: &mut wgpu::RenderPass<'rps>,
+        instance_buffer: &'rps wgpu::Buffer,
+        instances: u32,
+    ) {
+        render_pass.set_pipeline(&self.material.pipeline);
+        self.material.bind_group.set(render_pass);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        if let Some(i_buff) = &self.index_buffer {
+            render_pass.set_index_buffer(i_buff.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..self.num_indices.unwrap(), 0, 0..instances);
+        } else {
+            render_pass.draw(0..self.num_vertices, 0..instances);
+        }
+    }
+}
+
+/// A set of placements of one shared [`Model`]. The model lives behind an [`Arc`]
+/// so the same loaded asset can be referenced by many logical instances without
+/// re-uploading its GPU resources; the per-instance transforms are flushed into a
+/// single instance buffer and drawn in one batched call.
+pub struct ModelInstances {
+    model: Arc<Model>,
+    transforms: Vec<InstanceRaw>,
+    instance_buffer: Option<wgpu::Buffer>,
+}
+
+impl ModelInstances {
+    pub fn new(model: Arc<Model>) -> Self {
+        Self {
+            model,
+            transforms: Vec::new(),
+            instance_buffer: None,
+        }
+    }
+
+    /// Registers another placement of the shared model. The instance buffer is
+    /// rebuilt on the next [`flush`](Self::flush).
+    pub fn push(&mut self, transform: [[f32; 4]; 4]) {
+        self.transforms.push(InstanceRaw { model: transform });
+        self.instance_buffer = None;
+    }
+
+    /// Uploads the registered transforms into the instance buffer, ready to be
+    /// drawn by [`render`](Self::render).
+    pub fn flush(&mut self, device: &wgpu::Device) {
+        self.instance_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&self.transforms),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+    }
+
+    /// Issues a single instanced draw covering every registered placement. Call
+    /// [`flush`](Self::flush) first to (re)build the instance buffer.
+    pub fn render<'rps>(&'rps self, render_pass: &mut wgpu::RenderPass<'rps>) {
+        if let Some(buffer) = &self.instance_buffer {
+            self.model
+                .render_instanced(render_pass, buffer, self.transforms.len() as u32);
+        }
+    }
 }
\ No newline at end of file